@@ -0,0 +1,253 @@
+//! Admin API: on-demand refresh and config reload, protected by time-bounded
+//! API keys.
+//!
+//! Keys are supplied out-of-band via the `ADMIN_API_KEYS` environment
+//! variable as a JSON array, e.g.
+//! `[{"key":"...","not_before":1700000000,"not_after":1800000000}]`, so they
+//! can be rotated without recompiling or touching `netviz.toml`. A key's
+//! `not_before`/`not_after` (Unix seconds) are optional; omitting both makes
+//! it valid indefinitely.
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::state::AppState;
+
+const ADMIN_API_KEYS_ENV: &str = "ADMIN_API_KEYS";
+
+/// One admin API key and its optional validity window.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdminKey {
+    pub key: String,
+    #[serde(default)]
+    pub not_before: Option<u64>,
+    #[serde(default)]
+    pub not_after: Option<u64>,
+}
+
+/// Why an admin request was rejected, so the middleware can answer with the
+/// status code the caller needs: 403 for a key that was never issued, 401
+/// for one that was but isn't valid right now.
+enum AdminAuthError {
+    Unknown,
+    OutOfWindow,
+}
+
+impl IntoResponse for AdminAuthError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            AdminAuthError::Unknown => (StatusCode::FORBIDDEN, "unknown admin API key"),
+            AdminAuthError::OutOfWindow => (
+                StatusCode::UNAUTHORIZED,
+                "admin API key outside its validity window",
+            ),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+/// The parsed set of admin API keys, loaded once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct AdminKeySet(Vec<AdminKey>);
+
+impl AdminKeySet {
+    /// Loads and parses `ADMIN_API_KEYS`. An unset or empty env var yields an
+    /// empty key set, which rejects every admin request as "unknown key"
+    /// rather than silently disabling auth.
+    pub fn load_from_env() -> Self {
+        match std::env::var(ADMIN_API_KEYS_ENV) {
+            Ok(raw) if !raw.trim().is_empty() => match serde_json::from_str(&raw) {
+                Ok(keys) => Self(keys),
+                Err(e) => {
+                    warn!(
+                        "Failed to parse {}: {}. Admin API will reject all requests.",
+                        ADMIN_API_KEYS_ENV, e
+                    );
+                    Self::default()
+                }
+            },
+            _ => {
+                info!(
+                    "{} not set; admin API routes will reject all requests.",
+                    ADMIN_API_KEYS_ENV
+                );
+                Self::default()
+            }
+        }
+    }
+
+    fn validate(&self, presented: &str, now: u64) -> Result<(), AdminAuthError> {
+        let key = self
+            .0
+            .iter()
+            .find(|k| k.key == presented)
+            .ok_or(AdminAuthError::Unknown)?;
+
+        let after_start = key.not_before.map_or(true, |nb| now >= nb);
+        let before_end = key.not_after.map_or(true, |na| now <= na);
+
+        if after_start && before_end {
+            Ok(())
+        } else {
+            Err(AdminAuthError::OutOfWindow)
+        }
+    }
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Middleware guarding `/admin/*` routes: validates the `Authorization:
+/// Api-Key <key>` header against `state.admin_keys` before letting the
+/// request through.
+pub async fn require_admin_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Api-Key "));
+
+    let Some(presented) = presented else {
+        return AdminAuthError::Unknown.into_response();
+    };
+
+    match state.admin_keys.validate(presented, now_unix()) {
+        Ok(()) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// `POST /admin/refresh` - triggers an immediate `refresh_data` cycle
+/// instead of waiting for the next cron tick.
+pub async fn admin_refresh(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    info!("Admin: on-demand data refresh triggered");
+    crate::refresh_data(state).await;
+    Json(serde_json::json!({ "status": "refreshed" }))
+}
+
+/// `POST /admin/reload-config` - re-reads `netviz.toml` and swaps it into
+/// `state.config`. Note this does not rebuild the CORS/compression `tower`
+/// layers, which are only read once at router-build time; changes to those
+/// sections still require a restart.
+pub async fn admin_reload_config(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match Config::load() {
+        Ok(new_config) => {
+            *state.config.write().await = new_config;
+            info!("Admin: configuration reloaded from netviz.toml");
+            (StatusCode::OK, Json(serde_json::json!({ "status": "reloaded" })))
+        }
+        Err(e) => {
+            warn!("Admin: config reload failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_set(keys: Vec<AdminKey>) -> AdminKeySet {
+        AdminKeySet(keys)
+    }
+
+    fn key(key: &str, not_before: Option<u64>, not_after: Option<u64>) -> AdminKey {
+        AdminKey {
+            key: key.to_string(),
+            not_before,
+            not_after,
+        }
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected_as_forbidden() {
+        let keys = key_set(vec![key("known", None, None)]);
+        let result = keys.validate("unknown", 1_000);
+        assert!(matches!(result, Err(AdminAuthError::Unknown)));
+    }
+
+    #[test]
+    fn test_empty_key_set_rejects_everything_as_unknown() {
+        let keys = key_set(vec![]);
+        let result = keys.validate("anything", 1_000);
+        assert!(matches!(result, Err(AdminAuthError::Unknown)));
+    }
+
+    #[test]
+    fn test_open_ended_key_is_always_valid() {
+        let keys = key_set(vec![key("k", None, None)]);
+        assert!(keys.validate("k", 0).is_ok());
+        assert!(keys.validate("k", u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_key_valid_only_after_not_before() {
+        let keys = key_set(vec![key("k", Some(100), None)]);
+        assert!(matches!(
+            keys.validate("k", 99),
+            Err(AdminAuthError::OutOfWindow)
+        ));
+        assert!(keys.validate("k", 100).is_ok());
+        assert!(keys.validate("k", 1_000_000).is_ok());
+    }
+
+    #[test]
+    fn test_key_valid_only_before_not_after() {
+        let keys = key_set(vec![key("k", None, Some(200))]);
+        assert!(keys.validate("k", 0).is_ok());
+        assert!(keys.validate("k", 200).is_ok());
+        assert!(matches!(
+            keys.validate("k", 201),
+            Err(AdminAuthError::OutOfWindow)
+        ));
+    }
+
+    #[test]
+    fn test_key_valid_within_closed_window_inclusive_boundaries() {
+        let keys = key_set(vec![key("k", Some(100), Some(200))]);
+
+        assert!(matches!(
+            keys.validate("k", 99),
+            Err(AdminAuthError::OutOfWindow)
+        ));
+        assert!(keys.validate("k", 100).is_ok());
+        assert!(keys.validate("k", 150).is_ok());
+        assert!(keys.validate("k", 200).is_ok());
+        assert!(matches!(
+            keys.validate("k", 201),
+            Err(AdminAuthError::OutOfWindow)
+        ));
+    }
+
+    #[test]
+    fn test_validate_matches_by_exact_key_string() {
+        let keys = key_set(vec![key("k1", None, None), key("k2", Some(500), None)]);
+
+        assert!(keys.validate("k1", 0).is_ok());
+        assert!(matches!(
+            keys.validate("k2", 0),
+            Err(AdminAuthError::OutOfWindow)
+        ));
+    }
+}
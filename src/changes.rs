@@ -0,0 +1,277 @@
+//! Delta detection between PeeringDB refreshes.
+//!
+//! Before `refresh_data` swaps in a new `Vec<Network>`, it diffs the
+//! incoming dataset against the current one (keyed by `asn`) and records the
+//! result here: networks added, networks removed, and per-field changes for
+//! networks retained across the refresh. The last N change-sets are kept in
+//! a ring buffer on `AppState` and exposed via `GET /api/changes`.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::Network;
+
+/// A single field that differed between the old and new record for a network.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// A network retained across the refresh whose tracked fields changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkChange {
+    pub asn: i64,
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+}
+
+/// The result of diffing one PeeringDB refresh against the previous snapshot.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeSet {
+    /// Unix timestamp of the refresh this change-set was produced from.
+    pub timestamp: u64,
+    pub added: Vec<Network>,
+    pub removed: Vec<Network>,
+    pub changed: Vec<NetworkChange>,
+}
+
+/// Compares each tracked field of `old` and `new`, returning the ones that differ.
+fn diff_fields(old: &Network, new: &Network) -> Vec<FieldChange> {
+    let mut fields = Vec::new();
+
+    if old.name != new.name {
+        fields.push(FieldChange {
+            field: "name",
+            old: serde_json::json!(old.name),
+            new: serde_json::json!(new.name),
+        });
+    }
+    if old.info_prefixes4 != new.info_prefixes4 {
+        fields.push(FieldChange {
+            field: "info_prefixes4",
+            old: serde_json::json!(old.info_prefixes4),
+            new: serde_json::json!(new.info_prefixes4),
+        });
+    }
+    if old.info_prefixes6 != new.info_prefixes6 {
+        fields.push(FieldChange {
+            field: "info_prefixes6",
+            old: serde_json::json!(old.info_prefixes6),
+            new: serde_json::json!(new.info_prefixes6),
+        });
+    }
+    if old.policy_general != new.policy_general {
+        fields.push(FieldChange {
+            field: "policy_general",
+            old: serde_json::json!(old.policy_general),
+            new: serde_json::json!(new.policy_general),
+        });
+    }
+    if old.ix_count != new.ix_count {
+        fields.push(FieldChange {
+            field: "ix_count",
+            old: serde_json::json!(old.ix_count),
+            new: serde_json::json!(new.ix_count),
+        });
+    }
+    if old.fac_count != new.fac_count {
+        fields.push(FieldChange {
+            field: "fac_count",
+            old: serde_json::json!(old.fac_count),
+            new: serde_json::json!(new.fac_count),
+        });
+    }
+
+    fields
+}
+
+/// Diffs `new` against `old`, keyed by ASN, producing a [`ChangeSet`].
+///
+/// Only `name`, `info_prefixes4`, `info_prefixes6`, `policy_general`,
+/// `ix_count`, and `fac_count` are compared for networks present in both
+/// snapshots (see [`diff_fields`]); anything else (e.g. `website`, `aka`) is
+/// ignored so cosmetic or rarely-used fields don't generate noisy change-sets.
+pub fn diff(old: &[Network], new: &[Network], timestamp: u64) -> ChangeSet {
+    let old_by_asn: HashMap<i64, &Network> = old.iter().map(|n| (n.asn, n)).collect();
+    let new_by_asn: HashMap<i64, &Network> = new.iter().map(|n| (n.asn, n)).collect();
+
+    let added: Vec<Network> = new
+        .iter()
+        .filter(|n| !old_by_asn.contains_key(&n.asn))
+        .cloned()
+        .collect();
+    let removed: Vec<Network> = old
+        .iter()
+        .filter(|n| !new_by_asn.contains_key(&n.asn))
+        .cloned()
+        .collect();
+
+    let changed: Vec<NetworkChange> = new
+        .iter()
+        .filter_map(|new_network| {
+            let old_network = old_by_asn.get(&new_network.asn)?;
+            let fields = diff_fields(old_network, new_network);
+            if fields.is_empty() {
+                None
+            } else {
+                Some(NetworkChange {
+                    asn: new_network.asn,
+                    name: new_network.name.clone(),
+                    fields,
+                })
+            }
+        })
+        .collect();
+
+    ChangeSet {
+        timestamp,
+        added,
+        removed,
+        changed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(asn: i64, name: &str) -> Network {
+        Network {
+            id: asn,
+            name: name.to_string(),
+            asn,
+            aka: None,
+            status: None,
+            info_type: None,
+            policy_general: None,
+            info_scope: None,
+            info_prefixes4: None,
+            info_prefixes6: None,
+            ix_count: None,
+            fac_count: None,
+            website: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_detects_added_networks() {
+        let old = vec![network(1, "acme")];
+        let new = vec![network(1, "acme"), network(2, "widgets")];
+
+        let result = diff(&old, &new, 0);
+
+        assert_eq!(result.added.len(), 1);
+        assert_eq!(result.added[0].asn, 2);
+        assert!(result.removed.is_empty());
+        assert!(result.changed.is_empty());
+    }
+
+    #[test]
+    fn test_diff_detects_removed_networks() {
+        let old = vec![network(1, "acme"), network(2, "widgets")];
+        let new = vec![network(1, "acme")];
+
+        let result = diff(&old, &new, 0);
+
+        assert!(result.added.is_empty());
+        assert_eq!(result.removed.len(), 1);
+        assert_eq!(result.removed[0].asn, 2);
+        assert!(result.changed.is_empty());
+    }
+
+    #[test]
+    fn test_diff_keys_by_asn_not_id() {
+        // Same asn, different `id` -> still matched as the same network.
+        let mut old_network = network(1, "acme");
+        old_network.id = 100;
+        let mut new_network = network(1, "acme renamed");
+        new_network.id = 999;
+
+        let result = diff(&[old_network], &[new_network], 0);
+
+        assert!(result.added.is_empty());
+        assert!(result.removed.is_empty());
+        assert_eq!(result.changed.len(), 1);
+        assert_eq!(result.changed[0].asn, 1);
+    }
+
+    #[test]
+    fn test_diff_reports_no_change_when_no_tracked_field_differs() {
+        let mut old_network = network(1, "acme");
+        old_network.website = Some("https://old.example.com".to_string());
+        old_network.aka = Some("Old AKA".to_string());
+
+        let mut new_network = network(1, "acme");
+        // `website` and `aka` aren't tracked fields, so changing only them
+        // must not be reported.
+        new_network.website = Some("https://new.example.com".to_string());
+        new_network.aka = Some("New AKA".to_string());
+
+        let result = diff(&[old_network], &[new_network], 0);
+
+        assert!(result.changed.is_empty());
+    }
+
+    #[test]
+    fn test_diff_fields_reports_only_the_six_tracked_fields() {
+        let mut old_network = network(1, "acme");
+        let mut new_network = network(1, "acme new");
+        old_network.info_prefixes4 = Some(10);
+        new_network.info_prefixes4 = Some(20);
+        old_network.info_prefixes6 = Some(1);
+        new_network.info_prefixes6 = Some(2);
+        old_network.policy_general = Some("Open".to_string());
+        new_network.policy_general = Some("Selective".to_string());
+        old_network.ix_count = Some(1);
+        new_network.ix_count = Some(2);
+        old_network.fac_count = Some(1);
+        new_network.fac_count = Some(2);
+
+        let fields = diff_fields(&old_network, &new_network);
+        let field_names: Vec<&str> = fields.iter().map(|f| f.field).collect();
+
+        assert_eq!(
+            field_names,
+            vec![
+                "name",
+                "info_prefixes4",
+                "info_prefixes6",
+                "policy_general",
+                "ix_count",
+                "fac_count",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_fields_empty_when_nothing_tracked_changed() {
+        let old_network = network(1, "acme");
+        let new_network = network(1, "acme");
+
+        assert!(diff_fields(&old_network, &new_network).is_empty());
+    }
+
+    #[test]
+    fn test_diff_fields_captures_old_and_new_values() {
+        let mut old_network = network(1, "acme");
+        let mut new_network = network(1, "acme");
+        old_network.ix_count = Some(3);
+        new_network.ix_count = Some(5);
+
+        let fields = diff_fields(&old_network, &new_network);
+
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].field, "ix_count");
+        assert_eq!(fields[0].old, serde_json::json!(3));
+        assert_eq!(fields[0].new, serde_json::json!(5));
+    }
+
+    #[test]
+    fn test_diff_carries_through_the_provided_timestamp() {
+        let result = diff(&[], &[], 12_345);
+        assert_eq!(result.timestamp, 12_345);
+    }
+}
@@ -0,0 +1,301 @@
+//! Application configuration loaded from `netviz.toml`.
+//!
+//! Every field falls back to today's hardcoded value via `#[serde(default)]`,
+//! so an unconfigured checkout behaves exactly as before this module existed.
+//! This lets operators point NetViz at a PeeringDB mirror or private instance
+//! without recompiling.
+
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::error::NetVizError;
+
+/// Default location of the configuration file, relative to the working directory.
+const CONFIG_PATH: &str = "netviz.toml";
+
+fn default_peeringdb_base_url() -> String {
+    "https://www.peeringdb.com/api/".to_string()
+}
+
+fn default_data_dir() -> String {
+    "data/peeringdb".to_string()
+}
+
+fn default_bind_address() -> String {
+    "0.0.0.0:8201".to_string()
+}
+
+fn default_refresh_cron() -> String {
+    "0 0 0 * * *".to_string()
+}
+
+fn default_page_size() -> usize {
+    25
+}
+
+fn default_x_frame_options() -> String {
+    "DENY".to_string()
+}
+
+fn default_content_security_policy() -> String {
+    "default-src 'self'".to_string()
+}
+
+fn default_permissions_policy() -> String {
+    "geolocation=(), microphone=(), camera=()".to_string()
+}
+
+fn default_api_cache_seconds() -> u64 {
+    60
+}
+
+fn default_changes_history_size() -> usize {
+    20
+}
+
+/// Settings for the hardening-headers and cache-control middleware layer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecurityConfig {
+    /// Value for the `X-Frame-Options` response header.
+    #[serde(default = "default_x_frame_options")]
+    pub x_frame_options: String,
+    /// Value for the `Content-Security-Policy` response header.
+    #[serde(default = "default_content_security_policy")]
+    pub content_security_policy: String,
+    /// Value for the `Permissions-Policy` response header.
+    #[serde(default = "default_permissions_policy")]
+    pub permissions_policy: String,
+    /// `max-age` (in seconds) used in `Cache-Control` for `/api/*` JSON responses.
+    #[serde(default = "default_api_cache_seconds")]
+    pub api_cache_seconds: u64,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            x_frame_options: default_x_frame_options(),
+            content_security_policy: default_content_security_policy(),
+            permissions_policy: default_permissions_policy(),
+            api_cache_seconds: default_api_cache_seconds(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_compression_min_size_bytes() -> usize {
+    512
+}
+
+/// Settings for the negotiated response compression layer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompressionConfig {
+    /// Enable gzip compression.
+    #[serde(default = "default_true")]
+    pub gzip: bool,
+    /// Enable brotli compression.
+    #[serde(default = "default_true")]
+    pub brotli: bool,
+    /// Enable zstd compression.
+    #[serde(default = "default_true")]
+    pub zstd: bool,
+    /// Responses smaller than this (in bytes) are sent uncompressed.
+    #[serde(default = "default_compression_min_size_bytes")]
+    pub min_size_bytes: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            gzip: true,
+            brotli: true,
+            zstd: true,
+            min_size_bytes: default_compression_min_size_bytes(),
+        }
+    }
+}
+
+/// Settings for the `/api/*` CORS layer.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to read the `/api/*` JSON endpoints (e.g. `https://example.com`).
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// When `true`, allow any origin. Intended for local development only.
+    #[serde(default)]
+    pub permissive: bool,
+}
+
+/// Application configuration, deserialized from `netviz.toml` at startup.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Base URL for the PeeringDB API (or a compatible mirror/private instance).
+    #[serde(default = "default_peeringdb_base_url")]
+    pub peeringdb_base_url: String,
+    /// Directory where fetched PeeringDB JSON files are stored and read from.
+    #[serde(default = "default_data_dir")]
+    pub data_dir: String,
+    /// Address the HTTP server binds to.
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    /// Cron expression for the background data refresh schedule.
+    #[serde(default = "default_refresh_cron")]
+    pub refresh_cron: String,
+    /// Default number of items per page when a request omits `per_page`.
+    #[serde(default = "default_page_size")]
+    pub default_page_size: usize,
+    /// Optional PeeringDB API key. Falls back to `PEERINGDB_API_KEY` when unset.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Hardening-headers and cache-control settings.
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// Negotiated response compression settings.
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// CORS settings for the `/api/*` JSON endpoints.
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Number of past refresh change-sets kept in memory for `/api/changes`.
+    #[serde(default = "default_changes_history_size")]
+    pub changes_history_size: usize,
+    /// DNS-over-HTTPS endpoint used to resolve the PeeringDB API hostname
+    /// (e.g. `https://cloudflare-dns.com/dns-query`). Falls back to the
+    /// `DOH_RESOLVER` environment variable when unset; unset/empty means
+    /// resolve via the system stub resolver as before.
+    #[serde(default)]
+    pub doh_resolver: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            peeringdb_base_url: default_peeringdb_base_url(),
+            data_dir: default_data_dir(),
+            bind_address: default_bind_address(),
+            refresh_cron: default_refresh_cron(),
+            default_page_size: default_page_size(),
+            api_key: None,
+            security: SecurityConfig::default(),
+            compression: CompressionConfig::default(),
+            cors: CorsConfig::default(),
+            changes_history_size: default_changes_history_size(),
+            doh_resolver: None,
+        }
+    }
+}
+
+impl Config {
+    /// Loads configuration from `netviz.toml` in the working directory.
+    ///
+    /// Returns [`Config::default`] (today's hardcoded behavior) when the file
+    /// does not exist. A present-but-malformed file is a hard error so typos
+    /// in the config don't silently fall back to defaults.
+    pub fn load() -> Result<Self, NetVizError> {
+        Self::load_from(CONFIG_PATH)
+    }
+
+    fn load_from(path: impl AsRef<Path>) -> Result<Self, NetVizError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(NetVizError::from)
+    }
+
+    /// Resolves the effective PeeringDB API key: the config value if set,
+    /// otherwise the `PEERINGDB_API_KEY` environment variable.
+    pub fn resolved_api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .or_else(|| std::env::var("PEERINGDB_API_KEY").ok())
+    }
+
+    /// Path to the primary network data file within `data_dir`.
+    pub fn network_data_path(&self) -> std::path::PathBuf {
+        Path::new(&self.data_dir).join("net.json")
+    }
+
+    /// Resolves the effective DoH endpoint: the config value if set,
+    /// otherwise the `DOH_RESOLVER` environment variable. An empty string
+    /// from either source is treated as unset (system DNS).
+    pub fn resolved_doh_resolver(&self) -> Option<String> {
+        self.doh_resolver
+            .clone()
+            .or_else(|| std::env::var("DOH_RESOLVER").ok())
+            .filter(|s| !s.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_from_missing_file_yields_defaults() {
+        let config = Config::load_from("does-not-exist.toml").unwrap();
+
+        assert_eq!(config.peeringdb_base_url, default_peeringdb_base_url());
+        assert_eq!(config.data_dir, default_data_dir());
+        assert_eq!(config.bind_address, default_bind_address());
+        assert_eq!(config.refresh_cron, default_refresh_cron());
+        assert_eq!(config.default_page_size, default_page_size());
+        assert_eq!(config.changes_history_size, default_changes_history_size());
+        assert!(config.api_key.is_none());
+        assert!(config.doh_resolver.is_none());
+        assert_eq!(config.security.x_frame_options, default_x_frame_options());
+        assert!(config.compression.gzip);
+        assert!(!config.cors.permissive);
+        assert!(config.cors.allowed_origins.is_empty());
+    }
+
+    #[test]
+    fn test_load_from_malformed_file_is_hard_error() {
+        let dir = std::env::temp_dir().join(format!(
+            "netviz-config-test-malformed-{:?}",
+            std::thread::current().id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("netviz.toml");
+        std::fs::write(&path, "this = is not [valid toml").unwrap();
+
+        let result = Config::load_from(&path);
+
+        assert!(matches!(result, Err(NetVizError::ConfigParse(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_from_partial_file_fills_in_defaults() {
+        let dir = std::env::temp_dir().join(format!(
+            "netviz-config-test-partial-{:?}",
+            std::thread::current().id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("netviz.toml");
+        std::fs::write(&path, "bind_address = \"127.0.0.1:9000\"\n").unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+
+        assert_eq!(config.bind_address, "127.0.0.1:9000");
+        // Every other field still falls back to its hardcoded default.
+        assert_eq!(config.data_dir, default_data_dir());
+        assert_eq!(config.default_page_size, default_page_size());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_default_matches_load_from_missing_file() {
+        let defaults = Config::default();
+        let loaded = Config::load_from("also-does-not-exist.toml").unwrap();
+
+        assert_eq!(defaults.peeringdb_base_url, loaded.peeringdb_base_url);
+        assert_eq!(defaults.bind_address, loaded.bind_address);
+        assert_eq!(defaults.default_page_size, loaded.default_page_size);
+    }
+}
@@ -1,7 +1,9 @@
 //! Data loading module - handles reading network data from JSON files.
 
+use crate::config::Config;
 use crate::error::NetVizError;
 use crate::models::{Network, PeeringDBResponse};
+use polars::prelude::*;
 use std::fs;
 
 /// Loads network data from the PeeringDB JSON file.
@@ -14,13 +16,11 @@ use std::fs;
 /// - `Result<T, E>` is Rust's way of handling errors - it's either Ok(value) or Err(error)
 /// - `NetVizError` is our custom error type that provides clear error messages
 /// - The `?` operator below is shorthand for "return Err if this fails, otherwise unwrap Ok"
-pub fn load_network_data() -> Result<Vec<Network>, NetVizError> {
-    let file_path = "data/peeringdb/net.json";
-
+pub fn load_network_data(config: &Config) -> Result<Vec<Network>, NetVizError> {
     // `fs::read_to_string` reads entire file into a String
     // The `?` at the end propagates errors upward (returns early if error)
     // Thanks to `#[from]` in NetVizError, io::Error is automatically converted
-    let content = fs::read_to_string(file_path)?;
+    let content = fs::read_to_string(config.network_data_path())?;
 
     // Parse JSON into our struct. `serde_json::from_str` deserializes the JSON.
     // The `::<PeeringDBResponse<Network>>` is a "turbofish" - tells Rust the target type
@@ -31,3 +31,36 @@ pub fn load_network_data() -> Result<Vec<Network>, NetVizError> {
     // `Ok(...)` wraps the value in a successful Result
     Ok(response.data)
 }
+
+/// Builds the Polars `DataFrame` backing the `/api/*` analytics handlers
+/// from a freshly loaded `Vec<Network>`.
+pub fn build_dataframe(networks: &[Network]) -> PolarsResult<DataFrame> {
+    let ids: Vec<i64> = networks.iter().map(|n| n.id).collect();
+    let names: Vec<&str> = networks.iter().map(|n| n.name.as_str()).collect();
+    let asns: Vec<i64> = networks.iter().map(|n| n.asn).collect();
+    let info_types: Vec<Option<&str>> =
+        networks.iter().map(|n| n.info_type.as_deref()).collect();
+    let policy_generals: Vec<Option<&str>> = networks
+        .iter()
+        .map(|n| n.policy_general.as_deref())
+        .collect();
+    let info_scopes: Vec<Option<&str>> =
+        networks.iter().map(|n| n.info_scope.as_deref()).collect();
+    let info_prefixes4: Vec<Option<i64>> = networks.iter().map(|n| n.info_prefixes4).collect();
+    let info_prefixes6: Vec<Option<i64>> = networks.iter().map(|n| n.info_prefixes6).collect();
+    let ix_counts: Vec<Option<i64>> = networks.iter().map(|n| n.ix_count).collect();
+    let fac_counts: Vec<Option<i64>> = networks.iter().map(|n| n.fac_count).collect();
+
+    df!(
+        "id" => ids,
+        "name" => names,
+        "asn" => asns,
+        "info_type" => info_types,
+        "policy_general" => policy_generals,
+        "info_scope" => info_scopes,
+        "info_prefixes4" => info_prefixes4,
+        "info_prefixes6" => info_prefixes6,
+        "ix_count" => ix_counts,
+        "fac_count" => fac_counts,
+    )
+}
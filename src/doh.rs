@@ -0,0 +1,199 @@
+//! DNS-over-HTTPS resolution for the PeeringDB fetcher.
+//!
+//! When `Config::resolved_doh_resolver` is set, `fetch_and_save_peeringdb_data`
+//! resolves the PeeringDB API hostname via RFC 8484 JSON DoH queries instead
+//! of the system stub resolver, then hands reqwest the resolved address
+//! directly via `ClientBuilder::resolve`. This hardens data refresh against
+//! local DNS tampering or captive resolvers. Both A and AAAA records are
+//! queried (A first); the fetcher pins reqwest to the first address
+//! returned, so an IPv4 address is used whenever the host has an A record
+//! and an IPv6 address only when it has none. Resolved records are cached
+//! in memory for their advertised TTL so repeated refreshes don't repeat
+//! the DoH round trip unnecessarily.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::error::NetVizError;
+
+struct CachedAnswer {
+    addresses: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+fn cache() -> &'static Mutex<HashMap<String, CachedAnswer>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, CachedAnswer>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The subset of the RFC 8484 JSON response shape we need.
+#[derive(Debug, Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DohAnswer {
+    data: String,
+    #[serde(rename = "TTL")]
+    ttl: u64,
+}
+
+/// Queries a single DNS record type (`"A"` or `"AAAA"`) for `hostname` via
+/// the DoH endpoint at `doh_resolver`.
+async fn query_record_type(
+    client: &reqwest::Client,
+    doh_resolver: &str,
+    hostname: &str,
+    record_type: &str,
+) -> Result<DohResponse, NetVizError> {
+    let response = client
+        .get(doh_resolver)
+        .query(&[("name", hostname), ("type", record_type)])
+        .header("Accept", "application/dns-json")
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(response)
+}
+
+/// Resolves `hostname`'s A and AAAA records via the DoH endpoint at
+/// `doh_resolver`, returning a cached answer if one hasn't expired yet.
+pub async fn resolve(hostname: &str, doh_resolver: &str) -> Result<Vec<IpAddr>, NetVizError> {
+    if let Some(cached) = cache().lock().unwrap().get(hostname) {
+        if cached.expires_at > Instant::now() {
+            return Ok(cached.addresses.clone());
+        }
+    }
+
+    info!("Resolving '{}' via DoH endpoint {}", hostname, doh_resolver);
+
+    let client = reqwest::Client::new();
+    let mut answers = Vec::new();
+    let mut any_query_succeeded = false;
+
+    for record_type in ["A", "AAAA"] {
+        match query_record_type(&client, doh_resolver, hostname, record_type).await {
+            Ok(response) => {
+                any_query_succeeded = true;
+                answers.extend(response.answer);
+            }
+            Err(e) => warn!(
+                "DoH {} query for '{}' failed: {}",
+                record_type, hostname, e
+            ),
+        }
+    }
+
+    if !any_query_succeeded {
+        return Err(NetVizError::InvalidApiResponse(format!(
+            "DoH resolution for '{}' failed for both A and AAAA queries",
+            hostname
+        )));
+    }
+
+    let addresses: Vec<IpAddr> = answers
+        .iter()
+        .filter_map(|a| a.data.parse::<IpAddr>().ok())
+        .collect();
+
+    if addresses.is_empty() {
+        warn!(
+            "DoH resolution for '{}' returned no usable A/AAAA records",
+            hostname
+        );
+        return Ok(addresses);
+    }
+
+    cache().lock().unwrap().insert(
+        hostname.to_string(),
+        CachedAnswer {
+            addresses: addresses.clone(),
+            expires_at: Instant::now() + cache_ttl(&answers),
+        },
+    );
+
+    Ok(addresses)
+}
+
+/// The cache lifetime for a set of answers: the lowest TTL among them (so the
+/// cache never outlives the shortest-lived record), defaulting to 60 seconds
+/// when there are no answers, and floored at 1 second so a TTL of 0 doesn't
+/// turn every lookup into a fresh DoH round trip.
+fn cache_ttl(answers: &[DohAnswer]) -> Duration {
+    let min_ttl = answers.iter().map(|a| a.ttl).min().unwrap_or(60);
+    Duration::from_secs(min_ttl.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(data: &str, ttl: u64) -> DohAnswer {
+        DohAnswer {
+            data: data.to_string(),
+            ttl,
+        }
+    }
+
+    #[test]
+    fn test_cache_ttl_uses_the_minimum_across_answers() {
+        let answers = vec![answer("1.2.3.4", 300), answer("::1", 60)];
+        assert_eq!(cache_ttl(&answers), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_cache_ttl_defaults_to_sixty_seconds_when_no_answers() {
+        assert_eq!(cache_ttl(&[]), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_cache_ttl_floors_a_zero_ttl_at_one_second() {
+        let answers = vec![answer("1.2.3.4", 0)];
+        assert_eq!(cache_ttl(&answers), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_cached_answer_is_fresh_before_its_expiry() {
+        let cached = CachedAnswer {
+            addresses: vec!["1.2.3.4".parse().unwrap()],
+            expires_at: Instant::now() + Duration::from_secs(60),
+        };
+        assert!(cached.expires_at > Instant::now());
+    }
+
+    #[test]
+    fn test_cached_answer_is_stale_after_its_expiry() {
+        let cached = CachedAnswer {
+            addresses: vec!["1.2.3.4".parse().unwrap()],
+            expires_at: Instant::now() - Duration::from_secs(1),
+        };
+        assert!(cached.expires_at <= Instant::now());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_returns_cached_addresses_without_querying() {
+        let hostname = "cache-hit-test-host.example";
+        let cached_addr: IpAddr = "203.0.113.5".parse().unwrap();
+        cache().lock().unwrap().insert(
+            hostname.to_string(),
+            CachedAnswer {
+                addresses: vec![cached_addr],
+                expires_at: Instant::now() + Duration::from_secs(60),
+            },
+        );
+
+        // An unreachable resolver URL proves the cache hit short-circuits
+        // before any network call is made.
+        let result = resolve(hostname, "http://127.0.0.1:1").await.unwrap();
+
+        assert_eq!(result, vec![cached_addr]);
+    }
+}
@@ -4,7 +4,12 @@
 //! descriptive error messages. Using custom errors instead of `Box<dyn Error>`
 //! provides better type safety and more informative error handling.
 
+use axum::{
+    http::{header::ACCEPT, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+};
 use thiserror::Error;
+use tracing::error;
 
 /// Custom error type for NetViz operations.
 ///
@@ -37,4 +42,111 @@ pub enum NetVizError {
     /// Error when API returns unexpected data format.
     #[error("Invalid API response: {0}")]
     InvalidApiResponse(String),
+
+    /// Error parsing `netviz.toml`.
+    /// Automatically converts from `toml::de::Error`.
+    #[error("Config parse error: {0}")]
+    ConfigParse(#[from] toml::de::Error),
+
+    /// Error building or querying a Polars `DataFrame` (e.g. a missing
+    /// column or a type mismatch after a schema change upstream).
+    /// Automatically converts from `polars::error::PolarsError`.
+    #[error("Polars error: {0}")]
+    Polars(#[from] polars::error::PolarsError),
+
+    /// Error rendering a Tera template (missing template, context mismatch).
+    /// Automatically converts from `tera::Error`.
+    #[error("Template render error: {0}")]
+    TemplateRender(#[from] tera::Error),
+
+    /// A request's query parameters failed validation beyond what the
+    /// `Query<T>` extractor itself checks.
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+}
+
+impl NetVizError {
+    /// Maps each variant to the HTTP status a client should see.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            NetVizError::HttpRequest(_) => StatusCode::BAD_GATEWAY,
+            NetVizError::InvalidApiResponse(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            NetVizError::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+            NetVizError::Io(_)
+            | NetVizError::JsonParse(_)
+            | NetVizError::InvalidHeader(_)
+            | NetVizError::ConfigParse(_)
+            | NetVizError::Polars(_)
+            | NetVizError::TemplateRender(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// A short machine-readable tag identifying the variant, included in the
+    /// JSON error body so API clients can branch on it without parsing `message`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NetVizError::Io(_) => "io",
+            NetVizError::JsonParse(_) => "json_parse",
+            NetVizError::HttpRequest(_) => "http_request",
+            NetVizError::InvalidHeader(_) => "invalid_header",
+            NetVizError::InvalidApiResponse(_) => "invalid_api_response",
+            NetVizError::ConfigParse(_) => "config_parse",
+            NetVizError::Polars(_) => "polars",
+            NetVizError::TemplateRender(_) => "template_render",
+            NetVizError::InvalidQuery(_) => "invalid_query",
+        }
+    }
+
+    /// Renders this error as JSON or as a minimal HTML error page, depending
+    /// on whether the request's `Accept` header asks for HTML. Intended for
+    /// handlers that can serve either a browser page or a JSON API response
+    /// (e.g. the template-render failure path in `handlers.rs`); the `/api/*`
+    /// handlers call the blanket `IntoResponse` impl below on their errors,
+    /// which is always JSON.
+    pub fn into_response_for(self, headers: &HeaderMap) -> Response {
+        let wants_html = headers
+            .get(ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| {
+                accept.contains("text/html") && !accept.contains("application/json")
+            });
+
+        if wants_html {
+            let status = self.status_code();
+            let body = format!(
+                "<!DOCTYPE html><html><head><title>{status}</title></head>\
+                 <body><h1>{reason}</h1><p>{message}</p></body></html>",
+                status = status.as_u16(),
+                reason = status.canonical_reason().unwrap_or("Error"),
+                message = html_escape(&self.to_string()),
+            );
+            (status, Html(body)).into_response()
+        } else {
+            self.into_response()
+        }
+    }
+}
+
+/// Escapes the handful of characters that matter when splicing a plain-text
+/// error message into a hand-written HTML error page.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl IntoResponse for NetVizError {
+    /// Always renders as JSON (`{"error": ..., "kind": ...}`); see
+    /// [`NetVizError::into_response_for`] for Accept-negotiated HTML.
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let kind = self.kind();
+        error!("{}: {}", kind, self);
+        (
+            status,
+            Json(serde_json::json!({ "error": self.to_string(), "kind": kind })),
+        )
+            .into_response()
+    }
 }
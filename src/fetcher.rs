@@ -3,43 +3,217 @@
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{error, info, warn};
 
-/// Base URL for the PeeringDB API
-const BASE_API_URL: &str = "https://www.peeringdb.com/api/";
+use crate::config::Config;
+use crate::doh;
 
-/// Directory where we save downloaded JSON files
-const OUTPUT_DIR: &str = "data/peeringdb";
+/// Name of the file (inside `data_dir`) that records the server time of the
+/// last successful full or incremental PeeringDB fetch.
+const LAST_SYNC_FILE: &str = ".last_sync";
+
+/// Subtracted from the parsed server time before it's persisted as the next
+/// `since` cursor, so that sub-second clock/rounding differences between the
+/// API server that stamped `updated` and the one that stamped our `Date`
+/// response header can't cause a record to be skipped.
+const CLOCK_SKEW_MARGIN_SECS: u64 = 5;
+
+/// Current Unix timestamp. Only used as a last-resort `since` cursor when
+/// the PeeringDB response carries no parseable `Date` header.
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Parses an RFC 7231 IMF-fixdate `Date` header value (e.g.
+/// `"Sun, 06 Nov 1994 08:49:37 GMT"`) into a Unix timestamp. Returns `None`
+/// for anything else, including the legacy RFC 850 / asctime date formats
+/// that HTTP servers are permitted but rarely used to send.
+fn parse_http_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, _tz] = parts.as_slice() else {
+        return None;
+    };
+
+    let day: i64 = day.parse().ok()?;
+    let month = match *month {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = year.parse().ok()?;
+
+    let time_parts: Vec<&str> = time.split(':').collect();
+    let [hour, minute, second] = time_parts.as_slice() else {
+        return None;
+    };
+    let hour: i64 = hour.parse().ok()?;
+    let minute: i64 = minute.parse().ok()?;
+    let second: i64 = second.parse().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    u64::try_from(secs).ok()
+}
+
+/// Days since the Unix epoch (1970-01-01) for a given civil (Gregorian)
+/// date. Howard Hinnant's `days_from_civil` algorithm; the arithmetic
+/// overflows `i64` for years beyond roughly ±2.5e16.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Reads the last-sync timestamp, if one has been recorded for this data dir.
+fn read_last_sync(data_dir: &Path) -> Option<u64> {
+    let content = fs::read_to_string(data_dir.join(LAST_SYNC_FILE)).ok()?;
+    content.trim().parse().ok()
+}
+
+/// Persists the last-sync timestamp alongside the fetched JSON files.
+fn write_last_sync(data_dir: &Path, timestamp: u64) -> std::io::Result<()> {
+    fs::write(data_dir.join(LAST_SYNC_FILE), timestamp.to_string())
+}
+
+/// Merges a `since`-filtered delta response into an existing endpoint JSON
+/// file: upserts records by `id`, and drops records whose `status` is
+/// `"deleted"`. Returns the merged `PeeringDBResponse`-shaped value, keeping
+/// the existing file's `meta` block (falling back to the delta's) so a merge
+/// doesn't strip metadata the initial full snapshot wrote.
+fn merge_delta(existing: Value, delta: Value) -> Value {
+    let mut records = existing["data"].as_array().cloned().unwrap_or_default();
+    let delta_records = delta["data"].as_array().cloned().unwrap_or_default();
+
+    for record in delta_records {
+        let Some(id) = record.get("id") else {
+            continue;
+        };
+        let position = records.iter().position(|r| r.get("id") == Some(id));
+        let is_deleted = record.get("status").and_then(Value::as_str) == Some("deleted");
+
+        match (position, is_deleted) {
+            (Some(idx), true) => {
+                records.remove(idx);
+            }
+            (Some(idx), false) => records[idx] = record,
+            (None, true) => {}
+            (None, false) => records.push(record),
+        }
+    }
+
+    let mut merged = serde_json::json!({ "data": records });
+    if let Some(meta) = existing.get("meta").or_else(|| delta.get("meta")) {
+        merged["meta"] = meta.clone();
+    }
+    merged
+}
+
+/// Resolves the host in `base_url` via the configured DoH endpoint and
+/// returns `(hostname, resolved_socket_addr)` for `ClientBuilder::resolve`.
+/// Returns `None` (falling back to the system resolver) if `base_url` has no
+/// host or the DoH query fails, logging a warning in the latter case.
+async fn resolve_via_doh(base_url: &str, doh_resolver: &str) -> Option<(String, SocketAddr)> {
+    let url = reqwest::Url::parse(base_url).ok()?;
+    let host = url.host_str()?.to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    match doh::resolve(&host, doh_resolver).await {
+        Ok(addresses) if !addresses.is_empty() => {
+            let socket_addr = SocketAddr::new(addresses[0], port);
+            info!("Using DoH-resolved address {} for '{}'", socket_addr, host);
+            Some((host, socket_addr))
+        }
+        Ok(_) => {
+            warn!(
+                "DoH resolver returned no addresses for '{}'; falling back to system DNS",
+                host
+            );
+            None
+        }
+        Err(e) => {
+            warn!(
+                "DoH resolution failed for '{}': {}; falling back to system DNS",
+                host, e
+            );
+            None
+        }
+    }
+}
 
 /// Fetches all data from PeeringDB API and saves it as JSON files.
 ///
 /// # How it works
 /// 1. Creates the output directory if it doesn't exist
 /// 2. Fetches the API index to discover all available endpoints
-/// 3. Iterates through each endpoint and downloads its data
-/// 4. Saves each dataset as a separate JSON file
+/// 3. Iterates through each endpoint and downloads its data, using the
+///    `since` query parameter for an incremental delta once a prior full
+///    fetch has recorded a last-sync timestamp
+/// 4. Merges deltas into (or writes full snapshots over) each endpoint's
+///    JSON file, keeping the existing pretty-printed file layout
 ///
 /// # Rust Concepts
 /// - `async fn` - This function can be paused while waiting for I/O (like HTTP requests)
 /// - `.await` - Pauses execution until the async operation completes
 /// - `Result<(), ...>` - Returns Ok(()) on success (unit type), or an error
-pub async fn fetch_and_save_peeringdb_data() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn fetch_and_save_peeringdb_data(
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Create output directory (and parent directories if needed)
-    let output_path = PathBuf::from(OUTPUT_DIR);
+    let output_path = PathBuf::from(&config.data_dir);
     fs::create_dir_all(&output_path)?;
 
     // Build HTTP client with custom User-Agent (some APIs require this)
-    let client = reqwest::Client::builder()
-        .user_agent("NetViz/0.1.0")
-        .build()?;
+    let mut client_builder = reqwest::Client::builder().user_agent("NetViz/0.1.0");
 
-    info!("Fetching API index from {}...", BASE_API_URL);
+    // When a DoH resolver is configured, resolve the PeeringDB hostname
+    // ourselves and pin reqwest to that address instead of letting it fall
+    // through to the system stub resolver, which a captive network or local
+    // DNS tampering could otherwise redirect.
+    if let Some(doh_resolver) = config.resolved_doh_resolver() {
+        if let Some(host) = resolve_via_doh(&config.peeringdb_base_url, &doh_resolver).await {
+            client_builder = client_builder.resolve(&host.0, host.1);
+        }
+    }
+
+    let client = client_builder.build()?;
+
+    info!("Fetching API index from {}...", config.peeringdb_base_url);
 
     // `Value` is a generic JSON type - we use it when we don't know the exact structure
     // `.send().await` makes the HTTP request
     // `.json().await` parses the response body as JSON
-    let api_index: Value = client.get(BASE_API_URL).send().await?.json().await?;
+    let index_response = client.get(&config.peeringdb_base_url).send().await?;
+
+    // The `since` cursor has to be comparable to PeeringDB's server-side
+    // `updated` timestamps, so it's derived from this response's `Date`
+    // header rather than our local clock, which may be skewed.
+    let server_time = index_response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_http_date);
+
+    let api_index: Value = index_response.json().await?;
 
     // Navigate the JSON structure: data[0] contains the endpoint map
     // `.as_object()` tries to interpret it as a JSON object (returns Option)
@@ -48,16 +222,34 @@ pub async fn fetch_and_save_peeringdb_data() -> Result<(), Box<dyn std::error::E
         .as_object()
         .ok_or("Invalid API index format")?;
 
-    // Check for API key in environment variable (optional but recommended)
-    let api_key = std::env::var("PEERINGDB_API_KEY").unwrap_or_default();
+    // Check for an API key, either from config or the environment (optional but recommended)
     let mut headers = HeaderMap::new();
-    if !api_key.is_empty() {
+    if let Some(api_key) = config.resolved_api_key() {
         info!("API Key for PeeringDB found, using it.");
         let auth_value = format!("Api-Key {}", api_key);
         // `HeaderValue::from_str()` can fail if the string contains invalid chars
         headers.insert(AUTHORIZATION, HeaderValue::from_str(&auth_value)?);
     }
 
+    // A prior successful fetch lets us ask PeeringDB for only what changed
+    // since then, instead of re-downloading the entire dataset every cycle.
+    let since = read_last_sync(&output_path);
+    let fetch_started_at = match server_time {
+        Some(t) => t.saturating_sub(CLOCK_SKEW_MARGIN_SECS),
+        None => {
+            warn!(
+                "PeeringDB response had no parseable Date header; falling back to local clock for the sync cursor"
+            );
+            now_unix()
+        }
+    };
+
+    // Only advance the persisted cursor if at least one endpoint actually
+    // succeeds this cycle. Otherwise (e.g. a full PeeringDB outage), keep the
+    // old cursor so the next run doesn't incrementally skip over whatever
+    // changed while every fetch was failing.
+    let mut any_fetch_succeeded = false;
+
     // Iterate over all API endpoints
     for (name, url) in endpoints {
         // Handle invalid URLs gracefully - skip instead of aborting
@@ -70,31 +262,145 @@ pub async fn fetch_and_save_peeringdb_data() -> Result<(), Box<dyn std::error::E
         };
 
         let file_path = output_path.join(format!("{}.json", name));
+        let request_url = match since {
+            Some(ts) => format!("{}?since={}", url_str, ts),
+            None => url_str.to_string(),
+        };
 
-        info!("Fetching data for '{}' from {}...", name, url_str);
+        info!("Fetching data for '{}' from {}...", name, request_url);
 
         // `match` handles both success and error cases
         // `.headers(headers.clone())` attaches our auth headers
-        match client.get(url_str).headers(headers.clone()).send().await {
-            Ok(resp) => {
-                // Handle JSON parse failures explicitly instead of silently ignoring
-                match resp.json::<Value>().await {
-                    Ok(data) => {
-                        // Pretty-print JSON with indentation
-                        let json_data = serde_json::to_string_pretty(&data)?;
-                        // Write to file
+        match client
+            .get(&request_url)
+            .headers(headers.clone())
+            .send()
+            .await
+        {
+            Ok(resp) if since.is_some() && resp.status() == reqwest::StatusCode::BAD_REQUEST => {
+                // The `since` cursor may be too old for this endpoint's retention
+                // window; fall back to a full fetch rather than losing data.
+                warn!(
+                    "'{}' rejected since={:?} (cursor too old?), falling back to full fetch",
+                    name, since
+                );
+                match client.get(url_str).headers(headers.clone()).send().await {
+                    Ok(resp) => {
+                        if save_full_snapshot(name, &request_url, resp, &file_path).await? {
+                            any_fetch_succeeded = true;
+                        }
+                    }
+                    Err(e) => error!("Error fetching data from {}: {}", url_str, e),
+                }
+            }
+            Ok(resp) => match since {
+                None => {
+                    if save_full_snapshot(name, &request_url, resp, &file_path).await? {
+                        any_fetch_succeeded = true;
+                    }
+                }
+                Some(_) => match resp.json::<Value>().await {
+                    Ok(delta) => {
+                        let existing = fs::read_to_string(&file_path)
+                            .ok()
+                            .and_then(|s| serde_json::from_str(&s).ok())
+                            .unwrap_or_else(|| serde_json::json!({ "data": [] }));
+                        let merged = merge_delta(existing, delta);
+                        let json_data = serde_json::to_string_pretty(&merged)?;
                         fs::write(&file_path, json_data)?;
-                        info!("Successfully saved data to {:?}", file_path);
+                        info!("Merged incremental update for '{}' into {:?}", name, file_path);
+                        any_fetch_succeeded = true;
                     }
                     Err(e) => {
-                        error!("Failed to parse JSON from {}: {}", url_str, e);
-                        // Continue to next endpoint instead of failing
+                        error!("Failed to parse JSON from {}: {}", request_url, e);
                     }
-                }
-            }
-            Err(e) => error!("Error fetching data from {}: {}", url_str, e),
+                },
+            },
+            Err(e) => error!("Error fetching data from {}: {}", request_url, e),
         }
     }
 
+    if any_fetch_succeeded {
+        write_last_sync(&output_path, fetch_started_at)?;
+    } else {
+        warn!(
+            "All endpoint fetches failed this cycle; keeping previous sync cursor ({:?})",
+            since
+        );
+    }
+
     Ok(())
 }
+
+/// Writes a full (non-delta) endpoint response to disk, pretty-printed.
+/// Returns whether the snapshot was actually saved (`false` on a JSON parse
+/// error, which is logged here rather than propagated).
+async fn save_full_snapshot(
+    name: &str,
+    url_str: &str,
+    resp: reqwest::Response,
+    file_path: &Path,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    match resp.json::<Value>().await {
+        Ok(data) => {
+            let json_data = serde_json::to_string_pretty(&data)?;
+            fs::write(file_path, json_data)?;
+            info!("Successfully saved data to {:?}", file_path);
+            Ok(true)
+        }
+        Err(e) => {
+            error!("Failed to parse JSON from {}: {}", url_str, e);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_date_imf_fixdate() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777)
+        );
+    }
+
+    #[test]
+    fn test_parse_http_date_epoch() {
+        assert_eq!(
+            parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_parse_http_date_rejects_garbage() {
+        assert_eq!(parse_http_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_merge_delta_preserves_existing_meta() {
+        let existing = serde_json::json!({
+            "meta": { "generated": "2024-01-01T00:00:00Z" },
+            "data": [{ "id": 1, "name": "a" }]
+        });
+        let delta = serde_json::json!({ "data": [{ "id": 2, "name": "b" }] });
+
+        let merged = merge_delta(existing, delta);
+
+        assert_eq!(merged["meta"]["generated"], "2024-01-01T00:00:00Z");
+        assert_eq!(merged["data"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_merge_delta_without_any_meta_omits_it() {
+        let existing = serde_json::json!({ "data": [{ "id": 1, "name": "a" }] });
+        let delta = serde_json::json!({ "data": [] });
+
+        let merged = merge_delta(existing, delta);
+
+        assert!(merged.get("meta").is_none());
+    }
+}
@@ -1,18 +1,43 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Json},
+    http::HeaderMap,
+    response::{Html, IntoResponse, Json, Response},
 };
 use polars::prelude::*;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tera::Context;
-use tracing::error;
 
+use crate::error::NetVizError;
+use crate::metrics::record_aggregation_error;
 use crate::models::{Network, Stats};
+use crate::search::MAX_RESULTS;
 use crate::state::AppState;
 
+/// GET /api/changes - Recent refresh change-sets plus summary counts.
+///
+/// Returns the in-memory ring buffer of the last `changes_history_size`
+/// refreshes, most recent first, alongside totals the dashboard can render
+/// without having to sum the individual change-sets itself.
+pub async fn api_changes(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let changes_guard = state.changes.read().await;
+
+    let total_added: usize = changes_guard.iter().map(|c| c.added.len()).sum();
+    let total_removed: usize = changes_guard.iter().map(|c| c.removed.len()).sum();
+    let total_changed: usize = changes_guard.iter().map(|c| c.changed.len()).sum();
+
+    Json(serde_json::json!({
+        "changes": &*changes_guard,
+        "summary": {
+            "refreshes_tracked": changes_guard.len(),
+            "total_added": total_added,
+            "total_removed": total_removed,
+            "total_changed": total_changed,
+        }
+    }))
+}
+
 /// Query parameters for network list matching and pagination.
 #[derive(Debug, Deserialize)]
 pub struct NetworkQuery {
@@ -65,15 +90,18 @@ where
     }
 }
 
+/// Renders a Tera template. On failure, returns an error response negotiated
+/// from the request's `Accept` header (HTML error page or JSON body) instead
+/// of panicking on a missing template or a context type mismatch.
 fn render_template(
     tera: &tera::Tera,
     template: &str,
     context: &Context,
-) -> Result<Html<String>, (StatusCode, &'static str)> {
-    tera.render(template, context).map(Html).map_err(|e| {
-        error!("Template render error for '{}': {}", template, e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Render error")
-    })
+    headers: &HeaderMap,
+) -> Result<Html<String>, Response> {
+    tera.render(template, context)
+        .map(Html)
+        .map_err(|e| NetVizError::from(e).into_response_for(headers))
 }
 
 fn truncate_chars(s: &str, max_chars: usize) -> String {
@@ -86,8 +114,26 @@ fn truncate_chars(s: &str, max_chars: usize) -> String {
     }
 }
 
+/// Extracts a string column as owned `String`s, propagating a missing
+/// column or a non-string dtype as a `NetVizError` instead of panicking.
+fn str_column(df: &DataFrame, name: &str) -> Result<Vec<String>, NetVizError> {
+    Ok(df
+        .column(name)?
+        .str()?
+        .into_iter()
+        .flatten()
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Extracts an `i64` column, propagating a missing column or a non-`i64`
+/// dtype as a `NetVizError` instead of panicking.
+fn i64_column(df: &DataFrame, name: &str) -> Result<Vec<i64>, NetVizError> {
+    Ok(df.column(name)?.i64()?.into_iter().flatten().collect())
+}
+
 /// GET / - Dashboard with network statistics.
-pub async fn index(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub async fn index(State(state): State<Arc<AppState>>, headers: HeaderMap) -> impl IntoResponse {
     let data_guard = state.data.read().await;
     let networks = &data_guard.networks;
 
@@ -135,13 +181,14 @@ pub async fn index(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     drop(data_guard);
     context.insert("networks", &recent_networks);
 
-    render_template(&state.tera, "dashboard.html", &context)
+    render_template(&state.tera, "dashboard.html", &context, &headers)
 }
 
 /// GET /networks - Paginated network list.
 pub async fn networks_list(
     State(state): State<Arc<AppState>>,
     Query(query): Query<NetworkQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let data_guard = state.data.read().await;
     let networks = &data_guard.networks;
@@ -187,9 +234,10 @@ pub async fn networks_list(
         .cloned()
         .collect();
 
+    let default_page_size = state.config.read().await.default_page_size;
     let total_networks = filtered_networks.len();
     let page = query.page.unwrap_or(1).max(1);
-    let per_page = query.per_page.unwrap_or(25).clamp(1, 100);
+    let per_page = query.per_page.unwrap_or(default_page_size).clamp(1, 100);
     let total_pages = total_networks.div_ceil(per_page);
 
     // Adjust page if it exceeds total pages (unless total is 0)
@@ -222,44 +270,49 @@ pub async fn networks_list(
     context.insert("policy_filter", &query.policy);
     context.insert("status_filter", &query.status);
 
-    render_template(&state.tera, "networks.html", &context)
+    render_template(&state.tera, "networks.html", &context, &headers)
 }
 
 /// GET /analytics - Analytics dashboard.
-pub async fn analytics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub async fn analytics(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
     let context = Context::new();
-    render_template(&state.tera, "analytics.html", &context)
+    render_template(&state.tera, "analytics.html", &context, &headers)
 }
 
 /// GET /search - Search networks.
+///
+/// An exact ASN match is always returned first as a boosted hit. A `name`
+/// query is ranked by BM25 over a typo-tolerant inverted index, so
+/// misspellings and prefixes still surface relevant networks instead of
+/// requiring an exact substring match.
 pub async fn search_networks(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let data_guard = state.data.read().await;
     let networks = &data_guard.networks;
 
-    let search_name = query.name.as_ref().map(|n| {
-        let mut s = n.clone();
-        s.truncate(100);
-        s.to_lowercase()
-    });
-
-    let results: Vec<Network> = if query.asn.is_some() || search_name.is_some() {
-        networks
-            .iter()
-            .filter(|network| {
-                let matches_asn = query.asn == Some(network.asn);
-                let matches_name = search_name
-                    .as_ref()
-                    .is_some_and(|name| network.name.to_lowercase().contains(name));
-                matches_asn || matches_name
-            })
-            .cloned()
-            .collect()
-    } else {
-        Vec::new()
-    };
+    let mut results: Vec<Network> = Vec::new();
+
+    if let Some(asn) = query.asn {
+        results.extend(networks.iter().find(|n| n.asn == asn).cloned());
+    }
+
+    if let Some(name) = &query.name {
+        let mut name_query = name.clone();
+        name_query.truncate(100);
+
+        for hit in data_guard.search_index.search(&name_query, MAX_RESULTS) {
+            let network = &networks[hit.index];
+            if !results.iter().any(|r| r.id == network.id) {
+                results.push(network.clone());
+            }
+        }
+    }
     drop(data_guard);
 
     let mut context = Context::new();
@@ -267,67 +320,58 @@ pub async fn search_networks(
     context.insert("query_asn", &query.asn);
     context.insert("query_name", &query.name);
 
-    render_template(&state.tera, "search.html", &context)
+    render_template(&state.tera, "search.html", &context, &headers)
 }
 
-/// GET /api/network-types - JSON network type counts using Polars.
-pub async fn api_network_types(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let data_guard = state.data.read().await;
-    let df = &data_guard.df;
-
+/// Runs the network-type aggregation and extracts its columns, returning
+/// `Err` instead of panicking on a schema change or null-typed column.
+fn compute_network_types(df: &DataFrame) -> Result<(Vec<String>, Vec<usize>), NetVizError> {
     // Use Polars Lazy API for efficient aggregation
-    let agg_result = df
+    let agg = df
         .clone()
         .lazy()
         .filter(col("info_type").is_not_null())
         .group_by([col("info_type")])
         .agg([len().alias("count")])
+        .collect()?;
+
+    let labels = str_column(&agg, "info_type")?;
+    let counts: Vec<usize> = agg
+        .column("count")?
+        .cast(&DataType::UInt64)?
+        .u64()?
+        .into_iter()
+        .flatten()
+        .map(|v| v as usize)
         .collect();
 
-    drop(data_guard);
+    Ok((labels, counts))
+}
 
-    match agg_result {
-        Ok(res) => {
-            // Extract columns
-            let labels: Vec<String> = res
-                .column("info_type")
-                .ok()
-                .and_then(|s| s.str().ok())
-                .map(|ca| ca.into_iter().flatten().map(|s| s.to_string()).collect())
-                .unwrap_or_default();
-
-            let counts: Vec<usize> = if let Ok(s) = res.column("count") {
-                if let Ok(cast_s) = s.cast(&DataType::UInt64) {
-                    if let Ok(ca) = cast_s.u64() {
-                        ca.into_iter().flatten().map(|v| v as usize).collect()
-                    } else {
-                        vec![]
-                    }
-                } else {
-                    vec![]
-                }
-            } else {
-                vec![]
-            };
+/// GET /api/network-types - JSON network type counts using Polars.
+pub async fn api_network_types(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let data_guard = state.data.read().await;
+    let result = compute_network_types(&data_guard.df);
+    drop(data_guard);
 
-            Json(serde_json::json!({
-                "labels": labels,
-                "data": counts
-            }))
+    match result {
+        Ok((labels, counts)) => {
+            Json(serde_json::json!({ "labels": labels, "data": counts })).into_response()
         }
         Err(e) => {
-            error!("Polars aggregation error: {}", e);
-            Json(serde_json::json!({"labels": [], "data": []}))
+            record_aggregation_error();
+            e.into_response()
         }
     }
 }
 
-/// GET /api/prefixes-distribution - Top 15 networks by prefixes using Polars.
-pub async fn api_prefixes_distribution(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let data_guard = state.data.read().await;
-    let df = &data_guard.df;
-
-    let result = df
+/// Takes the first 15 networks (in data order, unsorted) that report both
+/// IPv4 and IPv6 prefix counts and extracts their columns, returning
+/// `Err` instead of panicking on a schema change or null-typed column.
+fn compute_prefixes_distribution(
+    df: &DataFrame,
+) -> Result<(Vec<String>, Vec<i64>, Vec<i64>), NetVizError> {
+    let res = df
         .clone()
         .lazy()
         .filter(
@@ -336,58 +380,52 @@ pub async fn api_prefixes_distribution(State(state): State<Arc<AppState>>) -> im
                 .and(col("info_prefixes6").is_not_null()),
         )
         .select([col("name"), col("info_prefixes4"), col("info_prefixes6")])
-        .limit(15) // Just take first 15 as in original code, or sort? Original used iter().take(15)
+        .limit(15)
+        .collect()?;
+
+    let names = str_column(&res, "name")?
+        .into_iter()
+        .map(|s| truncate_chars(&s, 30))
         .collect();
+    let ipv4 = i64_column(&res, "info_prefixes4")?;
+    let ipv6 = i64_column(&res, "info_prefixes6")?;
 
+    Ok((names, ipv4, ipv6))
+}
+
+/// GET /api/prefixes-distribution - Prefix counts of the first 15 networks
+/// reporting both IPv4 and IPv6 prefixes, using Polars.
+pub async fn api_prefixes_distribution(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let data_guard = state.data.read().await;
+    let result = compute_prefixes_distribution(&data_guard.df);
     drop(data_guard);
 
     match result {
-        Ok(res) => {
-            let names: Vec<String> = res
-                .column("name")
-                .ok()
-                .and_then(|s| s.str().ok())
-                .map(|ca| {
-                    ca.into_iter()
-                        .flatten()
-                        .map(|s| truncate_chars(s, 30))
-                        .collect()
-                })
-                .unwrap_or_default();
-
-            let ipv4: Vec<i64> = res
-                .column("info_prefixes4")
-                .ok()
-                .and_then(|s| s.i64().ok())
-                .map(|ca| ca.into_iter().flatten().collect())
-                .unwrap_or_default();
-
-            let ipv6: Vec<i64> = res
-                .column("info_prefixes6")
-                .ok()
-                .and_then(|s| s.i64().ok())
-                .map(|ca| ca.into_iter().flatten().collect())
-                .unwrap_or_default();
-
-            Json(serde_json::json!({
-                "networks": names,
-                "ipv4": ipv4,
-                "ipv6": ipv6
-            }))
-        }
+        Ok((names, ipv4, ipv6)) => Json(serde_json::json!({
+            "networks": names,
+            "ipv4": ipv4,
+            "ipv6": ipv6
+        }))
+        .into_response(),
         Err(e) => {
-            error!("Polars error: {}", e);
-            Json(serde_json::json!({"networks": [], "ipv4": [], "ipv6": []}))
+            record_aggregation_error();
+            e.into_response()
         }
     }
 }
 
-/// GET /api/ix-facility-correlation - Scatter plot data using Polars.
-pub async fn api_ix_facility_correlation(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let data_guard = state.data.read().await;
-    let df = &data_guard.df;
-
-    let result = df
+/// Runs the IX-vs-facility scatter query and builds the chart points,
+/// returning `Err` instead of panicking on a schema change or null-typed column.
+///
+/// Walks `ix_count`/`fac_count`/`name` together as a single row-wise pass
+/// over the selected frame, rather than collecting each column into its own
+/// `Vec` and zipping them afterwards: a null in just one column (`name` isn't
+/// covered by the `is_not_null` filter below) would otherwise shrink only
+/// that column's `Vec` and silently misalign every point's label from then on.
+fn compute_ix_facility_correlation(
+    df: &DataFrame,
+) -> Result<Vec<serde_json::Value>, NetVizError> {
+    let res = df
         .clone()
         .lazy()
         .filter(
@@ -396,50 +434,37 @@ pub async fn api_ix_facility_correlation(State(state): State<Arc<AppState>>) ->
                 .and(col("fac_count").is_not_null()),
         )
         .select([col("ix_count"), col("fac_count"), col("name")])
-        .collect();
+        .collect()?;
+
+    let ix_ca = res.column("ix_count")?.i64()?;
+    let fac_ca = res.column("fac_count")?.i64()?;
+    let name_ca = res.column("name")?.str()?;
+
+    Ok(ix_ca
+        .into_iter()
+        .zip(fac_ca.into_iter())
+        .zip(name_ca.into_iter())
+        .filter_map(|((x, y), name)| {
+            Some(serde_json::json!({
+                "x": x?,
+                "y": y?,
+                "label": name.unwrap_or("Unknown")
+            }))
+        })
+        .collect())
+}
 
+/// GET /api/ix-facility-correlation - Scatter plot data using Polars.
+pub async fn api_ix_facility_correlation(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let data_guard = state.data.read().await;
+    let result = compute_ix_facility_correlation(&data_guard.df);
     drop(data_guard);
 
     match result {
-        Ok(res) => {
-            let ix: Vec<i64> = res
-                .column("ix_count")
-                .unwrap()
-                .i64()
-                .unwrap()
-                .into_iter()
-                .flatten()
-                .collect();
-            let fac: Vec<i64> = res
-                .column("fac_count")
-                .unwrap()
-                .i64()
-                .unwrap()
-                .into_iter()
-                .flatten()
-                .collect();
-            let name_ca = res.column("name").unwrap().str().unwrap();
-
-            let points: Vec<_> = ix
-                .iter()
-                .zip(fac.iter())
-                .zip(name_ca.into_iter())
-                .filter_map(|((x, y), n)| {
-                    n.map(|name| {
-                        serde_json::json!({
-                            "x": x,
-                            "y": y,
-                            "label": name
-                        })
-                    })
-                })
-                .collect();
-
-            Json(points)
-        }
+        Ok(points) => Json(points).into_response(),
         Err(e) => {
-            error!("Polars error: {}", e);
-            Json(Vec::<serde_json::Value>::new())
+            record_aggregation_error();
+            e.into_response()
         }
     }
 }
@@ -470,5 +495,121 @@ mod tests {
         fn test_emoji_characters() {
             assert_eq!(truncate_chars("Hello 🌍🌍🌍", 8), "Hello 🌍🌍...");
         }
+
+        #[test]
+        fn test_empty_string() {
+            assert_eq!(truncate_chars("", 10), "");
+        }
+
+        #[test]
+        fn test_unicode_characters() {
+            assert_eq!(truncate_chars("こんにちは世界", 5), "こんにちは...");
+        }
+
+        #[test]
+        fn test_zero_max_chars() {
+            assert_eq!(truncate_chars("Hello", 0), "...");
+        }
+    }
+
+    mod pagination_tests {
+        /// Mirrors the page/per_page clamping and slice-index math in
+        /// `networks_list`, including the page-exceeds-total-pages
+        /// adjustment and the configurable `default_page_size` used when
+        /// `per_page` is omitted.
+        fn process_pagination(
+            page: Option<usize>,
+            per_page: Option<usize>,
+            default_page_size: usize,
+            total_networks: usize,
+        ) -> (usize, usize, usize, usize, usize) {
+            let page = page.unwrap_or(1).max(1);
+            let per_page = per_page.unwrap_or(default_page_size).clamp(1, 100);
+            let total_pages = total_networks.div_ceil(per_page);
+
+            let page = if total_pages > 0 && page > total_pages {
+                total_pages
+            } else {
+                page
+            };
+
+            let start_index = (page - 1).saturating_mul(per_page);
+            let end_index = start_index.saturating_add(per_page).min(total_networks);
+
+            (page, per_page, total_pages, start_index, end_index)
+        }
+
+        #[test]
+        fn test_page_defaults() {
+            let (page, per_page, ..) = process_pagination(None, None, 25, 100);
+            assert_eq!(page, 1);
+            assert_eq!(per_page, 25);
+        }
+
+        #[test]
+        fn test_page_zero_becomes_one() {
+            let (page, ..) = process_pagination(Some(0), None, 25, 100);
+            assert_eq!(page, 1);
+        }
+
+        #[test]
+        fn test_per_page_clamped_to_max() {
+            let (_, per_page, ..) = process_pagination(None, Some(200), 25, 100);
+            assert_eq!(per_page, 100);
+        }
+
+        #[test]
+        fn test_per_page_clamped_to_min() {
+            let (_, per_page, ..) = process_pagination(None, Some(0), 25, 100);
+            assert_eq!(per_page, 1);
+        }
+
+        #[test]
+        fn test_total_pages_calculation() {
+            let (_, _, total_pages, ..) = process_pagination(None, Some(25), 25, 101);
+            assert_eq!(total_pages, 5);
+        }
+
+        #[test]
+        fn test_slice_indices() {
+            let (_, _, _, start_index, end_index) =
+                process_pagination(Some(2), Some(25), 25, 100);
+            assert_eq!(start_index, 25);
+            assert_eq!(end_index, 50);
+        }
+
+        #[test]
+        fn test_last_page_partial() {
+            let (_, _, _, start_index, end_index) =
+                process_pagination(Some(5), Some(25), 25, 101);
+            assert_eq!(start_index, 100);
+            assert_eq!(end_index, 101);
+        }
+
+        #[test]
+        fn test_page_beyond_total_is_clamped_down() {
+            let (page, _, total_pages, start_index, end_index) =
+                process_pagination(Some(99), Some(25), 25, 101);
+            assert_eq!(total_pages, 5);
+            assert_eq!(page, 5);
+            assert_eq!(start_index, 100);
+            assert_eq!(end_index, 101);
+        }
+
+        #[test]
+        fn test_empty_index_has_no_pages() {
+            let (page, _, total_pages, start_index, end_index) =
+                process_pagination(Some(3), None, 25, 0);
+            assert_eq!(total_pages, 0);
+            assert_eq!(page, 3);
+            assert_eq!(start_index, 50);
+            assert_eq!(end_index, 0);
+        }
+
+        #[test]
+        fn test_default_page_size_from_config() {
+            let (_, per_page, ..) = process_pagination(None, None, 10, 100);
+            assert_eq!(per_page, 10);
+        }
     }
 }
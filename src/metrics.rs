@@ -0,0 +1,76 @@
+//! Observability subsystem: Prometheus metrics and request instrumentation.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::state::AppState;
+
+/// Installs the global Prometheus recorder and returns a handle that can
+/// render the current metrics in text-exposition format for `GET /metrics`.
+pub fn install_recorder() -> PrometheusHandle {
+    PrometheusBuilder::new()
+        .install_recorder()
+        .expect("failed to install Prometheus recorder")
+}
+
+/// `GET /metrics` - current metrics in Prometheus text exposition format.
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> String {
+    state.metrics_handle.render()
+}
+
+/// Records per-route request counts and latency histograms for every
+/// handled request, so operators can alert on error rates or slow routes.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let started_at = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status();
+    let elapsed = started_at.elapsed().as_secs_f64();
+
+    metrics::counter!(
+        "http_requests_total",
+        "route" => route.clone(),
+        "status" => status.as_u16().to_string(),
+    )
+    .increment(1);
+    metrics::histogram!("http_request_duration_seconds", "route" => route).record(elapsed);
+
+    response
+}
+
+/// Increments the Polars aggregation error counter for a failed `/api/*`
+/// dataframe query, as used by the `Err(e)` arms in `handlers.rs`.
+pub fn record_aggregation_error() {
+    metrics::counter!("polars_aggregation_errors_total").increment(1);
+}
+
+/// Sets the gauge tracking how many networks are currently loaded. Call this
+/// whenever the `RwLock<AppData>` is replaced, so it always reflects the
+/// live snapshot.
+pub fn set_networks_loaded(count: usize) {
+    metrics::gauge!("networks_loaded").set(count as f64);
+}
+
+/// Sets the gauge tracking the Unix timestamp of the most recent successful
+/// data refresh.
+pub fn set_last_refresh_timestamp(unix_seconds: u64) {
+    metrics::gauge!("last_refresh_timestamp_seconds").set(unix_seconds as f64);
+}
+
+/// Records the outcome and duration of one `refresh_data` cycle:
+/// `data_refresh_total{result}` and the `data_refresh_duration_seconds` histogram.
+pub fn record_refresh(result: &'static str, duration_seconds: f64) {
+    metrics::counter!("data_refresh_total", "result" => result).increment(1);
+    metrics::histogram!("data_refresh_duration_seconds").record(duration_seconds);
+}
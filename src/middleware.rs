@@ -0,0 +1,89 @@
+//! Axum middleware layers shared across all routes.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use tower_http::compression::{predicate::SizeAbove, CompressionLayer};
+use tower_http::cors::{Any, CorsLayer};
+
+use crate::config::{CompressionConfig, CorsConfig};
+use crate::state::AppState;
+
+/// Attaches hardening headers to every response, plus a route-appropriate
+/// `Cache-Control`: short-lived caching for `/api/*` JSON endpoints, and
+/// `no-store` for HTML pages. Values come from `Config::security` so the CSP
+/// and cache durations can be tuned without a rebuild.
+pub async fn security_headers(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_api_route = request.uri().path().starts_with("/api/");
+    let mut response = next.run(request).await;
+    let security = state.config.read().await.security.clone();
+    let headers = response.headers_mut();
+
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    if let Ok(value) = HeaderValue::from_str(&security.x_frame_options) {
+        headers.insert(header::X_FRAME_OPTIONS, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&security.content_security_policy) {
+        headers.insert(header::CONTENT_SECURITY_POLICY, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&security.permissions_policy) {
+        headers.insert(HeaderName::from_static("permissions-policy"), value);
+    }
+
+    let cache_control = if is_api_route {
+        format!("public, max-age={}", security.api_cache_seconds)
+    } else {
+        "no-store".to_string()
+    };
+    if let Ok(value) = HeaderValue::from_str(&cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+
+    response
+}
+
+/// Builds the negotiated response-compression layer (gzip/brotli/zstd),
+/// applied router-wide so both rendered HTML and `Json(...)` API responses
+/// are compressed according to the client's `Accept-Encoding`. Responses
+/// smaller than `min_size_bytes` are left uncompressed.
+pub fn compression_layer(config: &CompressionConfig) -> CompressionLayer<SizeAbove> {
+    CompressionLayer::new()
+        .gzip(config.gzip)
+        .br(config.brotli)
+        .zstd(config.zstd)
+        .deflate(false)
+        .compress_when(SizeAbove::new(config.min_size_bytes))
+}
+
+/// Builds the CORS layer applied to the `/api/*` routes, so chart-ready JSON
+/// can back a separate single-page app on another origin. `permissive`
+/// allows any origin for local development; otherwise only `allowed_origins`
+/// from `netviz.toml` may read the responses.
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let layer = CorsLayer::new()
+        .allow_methods([Method::GET])
+        .allow_headers(Any);
+
+    if config.permissive {
+        layer.allow_origin(Any)
+    } else {
+        let origins: Vec<HeaderValue> = config
+            .allowed_origins
+            .iter()
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect();
+        layer.allow_origin(origins)
+    }
+}
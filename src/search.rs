@@ -0,0 +1,392 @@
+//! Typo-tolerant full-text search over the loaded network data.
+//!
+//! Builds an in-memory inverted index (`term -> postings`) from each
+//! network's name, aka, and type/policy fields, then answers queries with
+//! fuzzy, Levenshtein-tolerant term matching scored by BM25. The index is
+//! rebuilt inside `refresh_data` (and at startup) whenever the underlying
+//! `Vec<Network>` changes, so it never drifts from the data it searches.
+
+use std::collections::HashMap;
+
+use crate::models::Network;
+
+/// BM25 term-frequency saturation parameter.
+const K1: f64 = 1.2;
+/// BM25 document-length normalization parameter.
+const B: f64 = 0.75;
+
+/// Maximum number of results returned by a single search.
+pub const MAX_RESULTS: usize = 50;
+
+/// Tokenizes text into lowercase terms, splitting on whitespace and punctuation.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_lowercase())
+        .collect()
+}
+
+/// Computes the Levenshtein edit distance between two strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0usize; b.len() + 1];
+
+    for (i, &ac) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, &bc) in b.iter().enumerate() {
+            let cost = if ac == bc { 0 } else { 1 };
+            curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Per-term postings: `(doc index, term frequency in that document)`.
+type Postings = Vec<(usize, usize)>;
+
+/// Result of a search: index into the source `Vec<Network>` and its BM25 score.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchHit {
+    pub index: usize,
+    pub score: f64,
+}
+
+/// In-memory inverted index over `name`, `aka`, `info_type`, and
+/// `policy_general`, rebuilt whenever the underlying network data refreshes.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    postings: HashMap<String, Postings>,
+    doc_len: Vec<usize>,
+    avg_doc_len: f64,
+    doc_count: usize,
+}
+
+impl SearchIndex {
+    /// Builds an index over `networks`, tokenizing each document's
+    /// name/aka/notes-like fields into a postings map plus per-document length.
+    pub fn build(networks: &[Network]) -> Self {
+        let mut postings: HashMap<String, Postings> = HashMap::new();
+        let mut doc_len = Vec::with_capacity(networks.len());
+
+        for (idx, network) in networks.iter().enumerate() {
+            let mut terms = tokenize(&network.name);
+            if let Some(aka) = &network.aka {
+                terms.extend(tokenize(aka));
+            }
+            if let Some(info_type) = &network.info_type {
+                terms.extend(tokenize(info_type));
+            }
+            if let Some(policy) = &network.policy_general {
+                terms.extend(tokenize(policy));
+            }
+
+            doc_len.push(terms.len());
+
+            let mut term_freq: HashMap<String, usize> = HashMap::new();
+            for term in terms {
+                *term_freq.entry(term).or_insert(0) += 1;
+            }
+            for (term, freq) in term_freq {
+                postings.entry(term).or_default().push((idx, freq));
+            }
+        }
+
+        let doc_count = networks.len();
+        let avg_doc_len = if doc_count == 0 {
+            0.0
+        } else {
+            doc_len.iter().sum::<usize>() as f64 / doc_count as f64
+        };
+
+        Self {
+            postings,
+            doc_len,
+            avg_doc_len,
+            doc_count,
+        }
+    }
+
+    /// BM25 inverse document frequency for a term appearing in `df` documents.
+    fn idf(&self, df: usize) -> f64 {
+        let n = self.doc_count as f64;
+        (((n - df as f64 + 0.5) / (df as f64 + 0.5)) + 1.0).ln()
+    }
+
+    /// Finds index terms within an edit-distance budget of `query_term` (1
+    /// for terms of 5 characters or fewer, 2 for longer terms), plus any
+    /// term that has `query_term` as a prefix when `allow_prefix` is set
+    /// (used for the final token, to support autocomplete-style queries).
+    fn fuzzy_terms(&self, query_term: &str, allow_prefix: bool) -> Vec<&str> {
+        let budget = if query_term.chars().count() <= 5 { 1 } else { 2 };
+
+        self.postings
+            .keys()
+            .filter(|term| {
+                (allow_prefix && term.starts_with(query_term.as_str()))
+                    || levenshtein(term, query_term) <= budget
+            })
+            .map(|s| s.as_str())
+            .collect()
+    }
+
+    /// Scores every candidate document against `query` using BM25 over
+    /// fuzzy-matched terms, returning the top `limit` hits by descending score.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_terms = tokenize(query);
+        if query_terms.is_empty() || self.doc_count == 0 {
+            return Vec::new();
+        }
+
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+
+        for (i, query_term) in query_terms.iter().enumerate() {
+            let is_last_token = i == query_terms.len() - 1;
+            for term in self.fuzzy_terms(query_term, is_last_token) {
+                let Some(postings) = self.postings.get(term) else {
+                    continue;
+                };
+                let idf = self.idf(postings.len());
+
+                for &(doc_idx, tf) in postings {
+                    let tf = tf as f64;
+                    let doc_len = self.doc_len[doc_idx] as f64;
+                    let denom = tf + K1 * (1.0 - B + B * doc_len / self.avg_doc_len.max(1.0));
+                    *scores.entry(doc_idx).or_insert(0.0) += idf * (tf * (K1 + 1.0)) / denom;
+                }
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .map(|(index, score)| SearchHit { index, score })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(id: i64, name: &str, asn: i64) -> Network {
+        Network {
+            id,
+            name: name.to_string(),
+            asn,
+            aka: None,
+            status: None,
+            info_type: None,
+            policy_general: None,
+            info_scope: None,
+            info_prefixes4: None,
+            info_prefixes6: None,
+            ix_count: None,
+            fac_count: None,
+            website: None,
+        }
+    }
+
+    mod tokenize_tests {
+        use super::*;
+
+        #[test]
+        fn test_splits_on_whitespace_and_punctuation() {
+            assert_eq!(
+                tokenize("Hello, World! Foo-Bar"),
+                vec!["hello", "world", "foo", "bar"]
+            );
+        }
+
+        #[test]
+        fn test_lowercases_terms() {
+            assert_eq!(tokenize("GOOGLE"), vec!["google"]);
+        }
+
+        #[test]
+        fn test_empty_string_yields_no_terms() {
+            assert!(tokenize("").is_empty());
+        }
+
+        #[test]
+        fn test_punctuation_only_yields_no_terms() {
+            assert!(tokenize("---, ...").is_empty());
+        }
+    }
+
+    mod levenshtein_tests {
+        use super::*;
+
+        #[test]
+        fn test_identical_strings() {
+            assert_eq!(levenshtein("google", "google"), 0);
+        }
+
+        #[test]
+        fn test_classic_kitten_sitting() {
+            assert_eq!(levenshtein("kitten", "sitting"), 3);
+        }
+
+        #[test]
+        fn test_against_empty_string_is_length() {
+            assert_eq!(levenshtein("", "abc"), 3);
+            assert_eq!(levenshtein("abc", ""), 3);
+        }
+
+        #[test]
+        fn test_single_substitution() {
+            assert_eq!(levenshtein("goggle", "google"), 1);
+        }
+    }
+
+    mod idf_tests {
+        use super::*;
+
+        #[test]
+        fn test_idf_never_negative_even_when_every_doc_matches() {
+            let networks = vec![network(1, "acme", 100), network(2, "acme", 200)];
+            let index = SearchIndex::build(&networks);
+
+            // Every document contains the term, so the raw BM25 IDF term
+            // would go negative; the `+ 1.0` shift keeps `ln(...)` >= 0.
+            let idf = index.idf(index.doc_count);
+            assert!(idf >= 0.0);
+        }
+
+        #[test]
+        fn test_idf_decreases_as_document_frequency_rises() {
+            let networks = vec![
+                network(1, "acme", 100),
+                network(2, "widgets", 200),
+                network(3, "gadgets", 300),
+            ];
+            let index = SearchIndex::build(&networks);
+
+            assert!(index.idf(1) > index.idf(2));
+            assert!(index.idf(2) > index.idf(3));
+        }
+    }
+
+    mod fuzzy_terms_tests {
+        use super::*;
+
+        #[test]
+        fn test_edit_distance_budget_is_one_at_five_chars() {
+            let networks = vec![network(1, "acme5", 100)];
+            let index = SearchIndex::build(&networks);
+
+            // "acme5" is 5 chars -> budget 1. "acmx5" is distance 1 away.
+            assert!(index
+                .fuzzy_terms("acmx5", false)
+                .contains(&"acme5"));
+            // Distance 2 away should NOT match at the 5-char budget.
+            assert!(!index.fuzzy_terms("axmx5", false).contains(&"acme5"));
+        }
+
+        #[test]
+        fn test_edit_distance_budget_is_two_above_five_chars() {
+            let networks = vec![network(1, "acmesix", 100)];
+            let index = SearchIndex::build(&networks);
+
+            // "acmesix" is 7 chars -> budget 2.
+            assert!(index.fuzzy_terms("axmesiy", false).contains(&"acmesix"));
+        }
+
+        #[test]
+        fn test_prefix_only_applies_when_allowed() {
+            let networks = vec![network(1, "acme", 100)];
+            let index = SearchIndex::build(&networks);
+
+            assert!(index.fuzzy_terms("ac", true).contains(&"acme"));
+            assert!(!index.fuzzy_terms("ac", false).contains(&"acme"));
+        }
+    }
+
+    mod search_tests {
+        use super::*;
+
+        #[test]
+        fn test_empty_query_returns_no_hits() {
+            let networks = vec![network(1, "acme", 100)];
+            let index = SearchIndex::build(&networks);
+
+            assert!(index.search("", 10).is_empty());
+            assert!(index.search("   ", 10).is_empty());
+        }
+
+        #[test]
+        fn test_empty_index_returns_no_hits_without_panicking() {
+            let index = SearchIndex::build(&[]);
+            assert!(index.search("acme", 10).is_empty());
+        }
+
+        #[test]
+        fn test_build_on_documents_with_no_terms_has_zero_avg_doc_len() {
+            let networks = vec![network(1, "", 100), network(2, "", 200)];
+            let index = SearchIndex::build(&networks);
+
+            assert_eq!(index.avg_doc_len, 0.0);
+            assert!(index.search("anything", 10).is_empty());
+        }
+
+        #[test]
+        fn test_hit_index_maps_back_to_the_matching_network() {
+            let networks = vec![
+                network(1, "zzz unrelated", 100),
+                network(2, "peeringdb", 200),
+            ];
+            let index = SearchIndex::build(&networks);
+
+            let hits = index.search("peeringdb", 10);
+
+            assert_eq!(hits.len(), 1);
+            assert_eq!(networks[hits[0].index].name, "peeringdb");
+        }
+
+        #[test]
+        fn test_typo_tolerant_match_via_fuzzy_terms() {
+            let networks = vec![network(1, "peeringdb", 100)];
+            let index = SearchIndex::build(&networks);
+
+            let hits = index.search("peeringdd", 10);
+
+            assert_eq!(hits.len(), 1);
+            assert_eq!(networks[hits[0].index].name, "peeringdb");
+        }
+
+        #[test]
+        fn test_results_sorted_by_descending_score() {
+            let networks = vec![
+                network(1, "acme widgets", 100),
+                network(2, "acme acme acme", 200),
+            ];
+            let index = SearchIndex::build(&networks);
+
+            let hits = index.search("acme", 10);
+
+            assert!(hits.len() >= 2);
+            for pair in hits.windows(2) {
+                assert!(pair[0].score >= pair[1].score);
+            }
+        }
+
+        #[test]
+        fn test_limit_truncates_results() {
+            let networks = (0..10)
+                .map(|i| network(i, "acme", 100 + i))
+                .collect::<Vec<_>>();
+            let index = SearchIndex::build(&networks);
+
+            assert_eq!(index.search("acme", 3).len(), 3);
+        }
+    }
+}
@@ -1,50 +1,81 @@
+use std::collections::VecDeque;
+
+use metrics_exporter_prometheus::PrometheusHandle;
 use polars::prelude::DataFrame;
-use std::env;
 use tera::Tera;
 use tokio::sync::RwLock;
 
+use crate::admin::AdminKeySet;
+use crate::changes::ChangeSet;
+use crate::config::Config;
 use crate::models::Network;
-
-/// Application configuration from environment variables.
-#[derive(Debug, Clone)]
-pub struct Config {
-    /// Address to bind the HTTP server to.
-    pub bind_address: String,
-    /// Cron expression for data refresh schedule.
-    pub refresh_cron: String,
-}
-
-impl Config {
-    /// Creates Config from environment variables with defaults.
-    pub fn from_env() -> Self {
-        Self {
-            bind_address: env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8201".into()),
-            refresh_cron: env::var("REFRESH_CRON").unwrap_or_else(|_| "0 0 0 * * *".into()),
-        }
-    }
-}
+use crate::search::SearchIndex;
 
 /// Shared application state passed to all request handlers.
-#[derive(Debug)]
 pub struct AppState {
     /// Template engine for rendering HTML pages.
     pub tera: Tera,
     /// Network data protected by RwLock.
     /// Stores both Vec (for templates) and DataFrame (for analytics).
     pub data: RwLock<AppData>,
+    /// Parsed `netviz.toml`, threaded through to the fetcher and loader so
+    /// they read endpoints/paths from config instead of string literals.
+    /// Behind a `RwLock` so `POST /admin/reload-config` can swap in a freshly
+    /// re-read file without a restart; readers take a short-lived read lock,
+    /// the same pattern used for `data`. Note the CORS and compression
+    /// `tower` layers are built once at startup from the initial config, so
+    /// changing `cors`/`compression` still requires a restart to take effect.
+    pub config: RwLock<Config>,
+    /// Renders the Prometheus text-exposition output for `GET /metrics`.
+    pub metrics_handle: PrometheusHandle,
+    /// Ring buffer of the last `config.changes_history_size` refresh
+    /// change-sets, most recent first. Separate from `data` since it's
+    /// cross-refresh history rather than a point-in-time snapshot.
+    pub changes: RwLock<VecDeque<ChangeSet>>,
+    /// Time-bounded API keys guarding the `/admin/*` routes, loaded once at
+    /// startup from the `ADMIN_API_KEYS` environment variable.
+    pub admin_keys: AdminKeySet,
 }
 
 #[derive(Debug)]
 pub struct AppData {
     pub networks: Vec<Network>,
     pub df: DataFrame,
+    /// Typo-tolerant search index, kept in lockstep with `networks` so a
+    /// reader never sees postings for a snapshot it can't also see.
+    pub search_index: SearchIndex,
 }
 
 impl AppState {
-    pub fn new(tera: Tera, networks: Vec<Network>, df: DataFrame) -> Self {
+    pub fn new(
+        tera: Tera,
+        networks: Vec<Network>,
+        df: DataFrame,
+        config: Config,
+        metrics_handle: PrometheusHandle,
+        admin_keys: AdminKeySet,
+    ) -> Self {
+        let search_index = SearchIndex::build(&networks);
         Self {
             tera,
-            data: RwLock::new(AppData { networks, df }),
+            data: RwLock::new(AppData {
+                networks,
+                df,
+                search_index,
+            }),
+            config: RwLock::new(config),
+            metrics_handle,
+            changes: RwLock::new(VecDeque::new()),
+            admin_keys,
         }
     }
+
+    /// Pushes a new change-set to the front of the ring buffer, trimming the
+    /// back down to `config.changes_history_size`.
+    pub async fn record_change_set(&self, change_set: ChangeSet) {
+        let changes_history_size = self.config.read().await.changes_history_size;
+        let mut changes_guard = self.changes.write().await;
+        changes_guard.push_front(change_set);
+        changes_guard.truncate(changes_history_size);
+    }
 }